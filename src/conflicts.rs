@@ -0,0 +1,277 @@
+use chrono::{DateTime, FixedOffset, NaiveDate};
+use chrono_tz::Tz;
+
+use crate::structs::{resolve_local, MonthlyShedding, Shedding};
+
+/// Two [`Shedding`] intervals whose times overlap, surfaced so a maintainer can resolve the
+/// contradiction in the source schedule rather than have it silently turn into two clashing
+/// calendar events.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Conflict {
+    /// The earlier-starting (or otherwise already "open") of the two overlapping events
+    pub a: Shedding,
+    /// The event whose start fell inside `a`'s interval
+    pub b: Shedding,
+    /// The start of the region where `a` and `b` overlap
+    pub overlap_start: DateTime<FixedOffset>,
+    /// The end of the region where `a` and `b` overlap
+    pub overlap_end: DateTime<FixedOffset>,
+}
+
+/// Expands a [`MonthlyShedding`] (whose `start_time`/`finsh_time` carry a pseudo-1970 date) into
+/// a concrete [`Shedding`] for a specific `(year, month)`, so it can be compared against other
+/// events using real `DateTime` ordering. Returns `None` if `date_of_month` doesn't exist in the
+/// given month (eg the 31st in April), or if the resulting local time is nonexistent in `tz`; an
+/// ambiguous local time (eg a DST fall-back) resolves to the earlier instant, same as
+/// [`resolve_local`] does for the manually-input schedule.
+pub fn expand_monthly_shedding(
+    monthly: &MonthlyShedding,
+    tz: Tz,
+    year: i32,
+    month: u32,
+) -> Option<Shedding> {
+    let date = NaiveDate::from_ymd_opt(year, month, u32::from(monthly.date_of_month))?;
+    let start = resolve_local(
+        "start_time",
+        &monthly.start_time.to_rfc3339(),
+        tz,
+        date.and_time(monthly.start_time.time()),
+    )
+    .ok()?;
+
+    let finsh_date = if monthly.goes_over_midnight {
+        date.succ_opt()?
+    } else {
+        date
+    };
+    let finsh = resolve_local(
+        "finsh_time",
+        &monthly.finsh_time.to_rfc3339(),
+        tz,
+        finsh_date.and_time(monthly.finsh_time.time()),
+    )
+    .ok()?;
+
+    Some(Shedding {
+        start,
+        finsh,
+        stage: monthly.stage,
+        source: format!(
+            "monthly schedule, day {} of the month",
+            monthly.date_of_month
+        ),
+    })
+}
+
+/// Sweeps `events` left to right (after sorting by `start`) looking for overlaps. Adjacent
+/// events that share a `stage` and are contiguous or overlapping are merged into a single
+/// `Shedding` spanning `min(start)..max(finsh)`; events with different stages that strictly
+/// overlap are reported as a [`Conflict`] instead, since a suburb can't genuinely be on two
+/// different loadshedding stages at once.
+///
+/// A new event is checked against every interval in the currently "active" set (those whose
+/// `finsh` hasn't yet been passed by the sweep), not just the single most recently pushed one —
+/// otherwise an event that only touches or conflicts with one active interval, without merging
+/// into it, would hide any still-open interval that started earlier from all later comparisons.
+///
+/// Events should already be expanded to absolute, time-zone-resolved intervals (see
+/// [`expand_monthly_shedding`] for `MonthlyShedding`) before being passed in here, so that
+/// overlap is a simple `DateTime` comparison rather than having to account for `goes_over_midnight`.
+pub fn merge_and_find_conflicts(mut events: Vec<Shedding>) -> (Vec<Shedding>, Vec<Conflict>) {
+    events.sort_by_key(|event| event.start);
+
+    let mut merged: Vec<Shedding> = Vec::new();
+    let mut conflicts = Vec::new();
+    // Indices into `merged` of intervals that might still overlap a later event, ie whose
+    // `finsh` hasn't yet been passed by the sweep.
+    let mut active: Vec<usize> = Vec::new();
+
+    for event in events {
+        active.retain(|&i| merged[i].finsh >= event.start);
+
+        let mut merged_into = false;
+        for &i in &active {
+            let touches_or_overlaps = event.start <= merged[i].finsh;
+            let strictly_overlaps = event.start < merged[i].finsh;
+
+            if merged[i].stage == event.stage && touches_or_overlaps {
+                if !merged_into {
+                    if event.start < merged[i].start {
+                        merged[i].start = event.start;
+                    }
+                    if event.finsh > merged[i].finsh {
+                        merged[i].finsh = event.finsh;
+                    }
+                    merged_into = true;
+                }
+            } else if merged[i].stage != event.stage && strictly_overlaps {
+                conflicts.push(Conflict {
+                    overlap_start: event.start,
+                    overlap_end: merged[i].finsh.min(event.finsh),
+                    a: merged[i].clone(),
+                    b: event.clone(),
+                });
+            }
+        }
+
+        if !merged_into {
+            merged.push(event);
+            active.push(merged.len() - 1);
+        }
+    }
+
+    (merged, conflicts)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn shedding(start: &str, finsh: &str, stage: u8) -> Shedding {
+        Shedding {
+            start: DateTime::parse_from_rfc3339(start).expect("valid fixture timestamp"),
+            finsh: DateTime::parse_from_rfc3339(finsh).expect("valid fixture timestamp"),
+            stage,
+            source: "test fixture".to_string(),
+        }
+    }
+
+    #[test]
+    fn non_overlapping_events_pass_through_unchanged() {
+        let events = vec![
+            shedding("2024-06-01T18:00:00+02:00", "2024-06-01T20:30:00+02:00", 4),
+            shedding("2024-06-02T18:00:00+02:00", "2024-06-02T20:30:00+02:00", 4),
+        ];
+
+        let (merged, conflicts) = merge_and_find_conflicts(events.clone());
+
+        assert_eq!(merged, events);
+        assert!(conflicts.is_empty());
+    }
+
+    #[test]
+    fn contiguous_same_stage_events_merge() {
+        let events = vec![
+            shedding("2024-06-01T18:00:00+02:00", "2024-06-01T20:30:00+02:00", 4),
+            shedding("2024-06-01T20:30:00+02:00", "2024-06-01T22:00:00+02:00", 4),
+        ];
+
+        let (merged, conflicts) = merge_and_find_conflicts(events);
+
+        assert_eq!(
+            merged,
+            vec![shedding(
+                "2024-06-01T18:00:00+02:00",
+                "2024-06-01T22:00:00+02:00",
+                4
+            )]
+        );
+        assert!(conflicts.is_empty());
+    }
+
+    #[test]
+    fn contiguous_different_stage_events_are_not_a_conflict() {
+        let events = vec![
+            shedding("2024-06-01T18:00:00+02:00", "2024-06-01T20:00:00+02:00", 4),
+            shedding("2024-06-01T20:00:00+02:00", "2024-06-01T22:00:00+02:00", 2),
+        ];
+
+        let (merged, conflicts) = merge_and_find_conflicts(events.clone());
+
+        assert_eq!(merged, events);
+        assert!(conflicts.is_empty());
+    }
+
+    #[test]
+    fn overlapping_different_stage_events_are_reported_as_conflicts() {
+        let a = shedding("2024-06-01T18:00:00+02:00", "2024-06-01T20:30:00+02:00", 4);
+        let b = shedding("2024-06-01T19:00:00+02:00", "2024-06-01T21:00:00+02:00", 2);
+
+        let (_merged, conflicts) = merge_and_find_conflicts(vec![a.clone(), b.clone()]);
+
+        assert_eq!(conflicts.len(), 1);
+        assert_eq!(conflicts[0].a, a);
+        assert_eq!(conflicts[0].b, b);
+        assert_eq!(
+            conflicts[0].overlap_start,
+            DateTime::parse_from_rfc3339("2024-06-01T19:00:00+02:00").unwrap()
+        );
+        assert_eq!(
+            conflicts[0].overlap_end,
+            DateTime::parse_from_rfc3339("2024-06-01T20:30:00+02:00").unwrap()
+        );
+    }
+
+    #[test]
+    fn a_later_event_is_checked_against_every_still_active_interval_not_just_the_last() {
+        // A (stage 4, 08:00-10:00) and B (stage 2, 08:30-09:00, nested inside A) correctly
+        // conflict. C (stage 4, 08:45-11:00) overlaps *both* A and B: it shares A's stage and
+        // should merge with it even though B was pushed more recently, and it strictly overlaps
+        // B's different-stage interval too, which should also be reported as a conflict.
+        let a = shedding("2024-06-01T08:00:00+02:00", "2024-06-01T10:00:00+02:00", 4);
+        let b = shedding("2024-06-01T08:30:00+02:00", "2024-06-01T09:00:00+02:00", 2);
+        let c = shedding("2024-06-01T08:45:00+02:00", "2024-06-01T11:00:00+02:00", 4);
+
+        let (merged, conflicts) =
+            merge_and_find_conflicts(vec![a.clone(), b.clone(), c.clone()]);
+
+        assert_eq!(
+            merged,
+            vec![
+                shedding("2024-06-01T08:00:00+02:00", "2024-06-01T11:00:00+02:00", 4),
+                b.clone(),
+            ]
+        );
+
+        assert_eq!(conflicts.len(), 2);
+        assert!(conflicts.iter().any(|conflict| conflict.a == a && conflict.b == b));
+        assert!(conflicts
+            .iter()
+            .any(|conflict| conflict.a == b && conflict.b == c));
+    }
+
+    #[test]
+    fn expand_monthly_shedding_crosses_midnight_into_the_next_day() {
+        let monthly = MonthlyShedding {
+            start_time: DateTime::parse_from_rfc3339("1970-01-01T22:00:00+02:00").unwrap(),
+            finsh_time: DateTime::parse_from_rfc3339("1970-01-02T00:30:00+02:00").unwrap(),
+            stage: 4,
+            date_of_month: 15,
+            goes_over_midnight: true,
+        };
+
+        let expanded = expand_monthly_shedding(&monthly, chrono_tz::Africa::Johannesburg, 2024, 6)
+            .expect("15 June 2024 is a valid date");
+
+        assert_eq!(
+            expanded.start,
+            DateTime::parse_from_rfc3339("2024-06-15T22:00:00+02:00").unwrap()
+        );
+        assert_eq!(
+            expanded.finsh,
+            DateTime::parse_from_rfc3339("2024-06-16T00:30:00+02:00").unwrap()
+        );
+    }
+
+    #[test]
+    fn expand_monthly_shedding_resolves_an_ambiguous_local_time_to_the_earlier_instant() {
+        // On 2024-11-03, America/New_York clocks fall back from 02:00 to 01:00, so 01:30 happens
+        // twice. This used to make the whole monthly event vanish (`.single()` returns `None`
+        // for ambiguous times too); it should resolve to the earlier instant instead.
+        let monthly = MonthlyShedding {
+            start_time: DateTime::parse_from_rfc3339("1970-01-01T01:30:00+00:00").unwrap(),
+            finsh_time: DateTime::parse_from_rfc3339("1970-01-01T03:00:00+00:00").unwrap(),
+            stage: 2,
+            date_of_month: 3,
+            goes_over_midnight: false,
+        };
+
+        let expanded = expand_monthly_shedding(&monthly, chrono_tz::America::New_York, 2024, 11)
+            .expect("an ambiguous local time should resolve rather than disappear");
+
+        assert_eq!(
+            expanded.start,
+            DateTime::parse_from_rfc3339("2024-11-03T01:30:00-04:00").unwrap()
+        );
+    }
+}
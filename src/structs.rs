@@ -1,10 +1,184 @@
+use std::fmt;
 use std::fmt::Debug;
 
-use chrono::{DateTime, FixedOffset, NaiveTime};
+// `Shedding`, `MonthlyShedding` and `ManuallyInputSchedule` below derive `Serialize`/
+// `Deserialize` directly on `DateTime<FixedOffset>` and `Tz` fields, which requires this crate's
+// Cargo.toml to enable chrono's `serde` feature and chrono-tz's `serde` feature — without both,
+// `DateTime<FixedOffset>` has no `Deserialize` impl and this module fails to compile.
+use chrono::{
+    DateTime, Duration, FixedOffset, LocalResult, NaiveDate, NaiveDateTime, NaiveTime, ParseError,
+    TimeZone,
+};
+use chrono_tz::Tz;
 use serde::{Deserialize, Serialize};
 
+/// The time zone assumed for schedule files that don't specify one. South Africa does not
+/// observe daylight saving time, so this has historically been indistinguishable from a fixed
+/// `+02:00` offset, but resolving it as a proper IANA zone keeps this crate correct if it is ever
+/// reused for a schedule in a zone that does.
+fn default_timezone_name() -> String {
+    "Africa/Johannesburg".to_string()
+}
+
+/// Parses `value` as a full datetime, trying a handful of formats that show up in hand-edited
+/// and copy-pasted schedule files, in order:
+///
+/// 1. a fully-qualified RFC3339 timestamp, offset preserved
+/// 2. RFC3339 with a space instead of `T` separating date and time
+/// 3. a bare `YYYY-MM-DDTHH:MM`, a local wall-clock time in `tz`
+/// 4. RFC2822 (eg `Tue, 1 Jul 2025 18:00:00 +0200`)
+/// 5. a bare `YYYY-MM-DD` date, taken as local midnight in `tz` on that day
+///
+/// When `roll_over_if_bare_date` is set, a bare date from case 5 is rolled forward to the
+/// following midnight, which is what `finsh` needs when it is used as an exclusive end bound.
+fn parse_datetime_field(
+    field: &'static str,
+    value: &str,
+    tz: Tz,
+    roll_over_if_bare_date: bool,
+) -> Result<DateTime<FixedOffset>, SheddingParseError> {
+    if let Ok(dt) = DateTime::parse_from_rfc3339(value) {
+        return Ok(dt);
+    }
+    if let Ok(dt) = DateTime::parse_from_rfc3339(&value.replacen(' ', "T", 1)) {
+        return Ok(dt);
+    }
+    if let Ok(naive) = NaiveDateTime::parse_from_str(value, "%Y-%m-%dT%H:%M") {
+        return resolve_local(field, value, tz, naive);
+    }
+
+    let last_err = match DateTime::parse_from_rfc2822(value) {
+        Ok(dt) => return Ok(dt),
+        Err(e) => e,
+    };
+
+    if let Ok(date) = NaiveDate::parse_from_str(value, "%Y-%m-%d") {
+        let date = if roll_over_if_bare_date {
+            date + Duration::days(1)
+        } else {
+            date
+        };
+        let naive = date
+            .and_hms_opt(0, 0, 0)
+            .expect("midnight is always a valid time of day");
+        return resolve_local(field, value, tz, naive);
+    }
+
+    Err(SheddingParseError::InvalidFormat {
+        field,
+        value: value.to_string(),
+        source: last_err,
+    })
+}
+
+/// Parses `value` as a bare time of day, trying `HH:MM` and then `HH:MM:SS`, since both show up
+/// in the monthly schedule files depending on who edited them last.
+fn parse_time_field(field: &'static str, value: &str) -> Result<NaiveTime, SheddingParseError> {
+    NaiveTime::parse_from_str(value, "%H:%M")
+        .or_else(|_| NaiveTime::parse_from_str(value, "%H:%M:%S"))
+        .map_err(|source| SheddingParseError::InvalidFormat {
+            field,
+            value: value.to_string(),
+            source,
+        })
+}
+
+/// Resolves a local wall-clock time in `tz` to a concrete instant, handling the ambiguous and
+/// nonexistent cases that daylight-saving transitions can produce (SAST has no DST, so in
+/// practice these never trigger for `Africa/Johannesburg`, but they can for other zones).
+/// An ambiguous time (eg 01:30 during a "fall back") resolves to the earlier of the two
+/// instants; a nonexistent time (eg 02:30 during a "spring forward") is reported as an error.
+pub(crate) fn resolve_local(
+    field: &'static str,
+    value: &str,
+    tz: Tz,
+    naive: NaiveDateTime,
+) -> Result<DateTime<FixedOffset>, SheddingParseError> {
+    match tz.from_local_datetime(&naive) {
+        LocalResult::Single(dt) => Ok(dt.fixed_offset()),
+        LocalResult::Ambiguous(earliest, _latest) => Ok(earliest.fixed_offset()),
+        LocalResult::None => Err(SheddingParseError::NonexistentLocalTime {
+            field,
+            value: value.to_string(),
+            timezone: tz,
+        }),
+    }
+}
+
+/// An error encountered while parsing a raw date/time field into its typed form.
+#[derive(Debug)]
+pub enum SheddingParseError {
+    /// The raw string didn't match any of the datetime/time formats we know how to parse.
+    InvalidFormat {
+        /// The name of the field that failed to parse, eg `"start"` or `"finsh_time"`
+        field: &'static str,
+        /// The raw string value that could not be parsed
+        value: String,
+        /// The underlying error returned by `chrono`
+        source: ParseError,
+    },
+    /// The raw string named a local wall-clock time that does not exist in `timezone`, eg it
+    /// falls inside a "spring forward" DST gap.
+    NonexistentLocalTime {
+        /// The name of the field that failed to parse, eg `"start"` or `"finsh_time"`
+        field: &'static str,
+        /// The raw string value that could not be parsed
+        value: String,
+        /// The time zone the value was interpreted in
+        timezone: Tz,
+    },
+    /// The `timezone` field of a schedule did not name a recognised IANA time zone.
+    UnknownTimezone {
+        /// The raw string value that could not be parsed
+        value: String,
+    },
+}
+
+impl fmt::Display for SheddingParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SheddingParseError::InvalidFormat {
+                field,
+                value,
+                source,
+            } => write!(
+                f,
+                "failed to parse field `{field}` with value `{value}`: {source}"
+            ),
+            SheddingParseError::NonexistentLocalTime {
+                field,
+                value,
+                timezone,
+            } => write!(
+                f,
+                "field `{field}` with value `{value}` does not exist in time zone `{timezone}`"
+            ),
+            SheddingParseError::UnknownTimezone { value } => {
+                write!(f, "`{value}` is not a recognised IANA time zone")
+            }
+        }
+    }
+}
+
+impl std::error::Error for SheddingParseError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            SheddingParseError::InvalidFormat { source, .. } => Some(source),
+            SheddingParseError::NonexistentLocalTime { .. }
+            | SheddingParseError::UnknownTimezone { .. } => None,
+        }
+    }
+}
+
 /// A multitude of load shedding for a particular suburb
+///
+/// Round-trips through serde: `timezone` is its IANA name, and `changes`/`historical_changes`
+/// serialize however [`Shedding`] does.
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
 pub struct ManuallyInputSchedule {
+    /// The time zone that `changes` and `historical_changes` were interpreted in. Defaults to
+    /// `Africa/Johannesburg`.
+    pub timezone: Tz,
     /// LoadShedding changes, usually in the future (but not always)
     pub changes: Vec<Shedding>,
     /// LoadShedding changes, always in the past
@@ -14,27 +188,67 @@ pub struct ManuallyInputSchedule {
 /// A multitude of load shedding for a particular suburb
 #[derive(Serialize, Deserialize)]
 pub struct RawManuallyInputSchedule {
+    /// The IANA name of the time zone that `changes` and `historical_changes` are in, eg
+    /// `"Africa/Johannesburg"`. Defaults to `Africa/Johannesburg` when absent.
+    #[serde(default = "default_timezone_name")]
+    timezone: String,
     /// LoadShedding changes, usually in the future (but not always)
     changes: Vec<RawShedding>,
     /// LoadShedding changes, always in the past
     historical_changes: Vec<RawShedding>,
 }
 
-impl From<RawManuallyInputSchedule> for ManuallyInputSchedule {
-    fn from(raw: RawManuallyInputSchedule) -> Self {
-        ManuallyInputSchedule {
-            changes: raw.changes.into_iter().map(|r| r.into()).collect(),
-            historical_changes: raw
-                .historical_changes
-                .into_iter()
-                .map(|r| r.into())
-                .collect(),
+impl TryFrom<RawManuallyInputSchedule> for ManuallyInputSchedule {
+    /// Every malformed entry in `changes` or `historical_changes` is collected here, rather than
+    /// bailing out on the first one, so a contributor editing the schedule files gets all of
+    /// their mistakes reported at once.
+    type Error = Vec<SheddingParseError>;
+
+    fn try_from(raw: RawManuallyInputSchedule) -> Result<Self, Self::Error> {
+        let timezone: Tz = raw.timezone.parse().map_err(|_| {
+            vec![SheddingParseError::UnknownTimezone {
+                value: raw.timezone.clone(),
+            }]
+        })?;
+
+        let mut errors = Vec::new();
+
+        let changes = raw
+            .changes
+            .into_iter()
+            .filter_map(|r| {
+                r.try_into_shedding(timezone)
+                    .map_err(|e| errors.push(e))
+                    .ok()
+            })
+            .collect();
+        let historical_changes = raw
+            .historical_changes
+            .into_iter()
+            .filter_map(|r| {
+                r.try_into_shedding(timezone)
+                    .map_err(|e| errors.push(e))
+                    .ok()
+            })
+            .collect();
+
+        if errors.is_empty() {
+            Ok(ManuallyInputSchedule {
+                timezone,
+                changes,
+                historical_changes,
+            })
+        } else {
+            Err(errors)
         }
     }
 }
 
 /// A single duration of loadshedding that only has one stage.
-#[derive(Debug)]
+///
+/// Round-trips through serde: `start` and `finsh` serialize as RFC3339 strings, `stage` as an
+/// integer, and `source` as a plain string.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
 pub struct Shedding {
     /// The time when LoadShedding *should* start
     pub start: DateTime<FixedOffset>,
@@ -60,26 +274,20 @@ pub struct RawShedding {
     source: String,
 }
 
-impl From<RawShedding> for Shedding {
-    fn from(raw: RawShedding) -> Self {
-        Shedding {
-            start: DateTime::parse_from_rfc3339(&format!("{}+02:00", raw.start)).expect(
-                format!(
-                    "Failed to parse start time 1970-01-01T{}:00+02:00 as RFC3339, {raw:?}",
-                    raw.start
-                )
-                .as_str(),
-            ),
-            finsh: DateTime::parse_from_rfc3339(&format!("{}+02:00", raw.finsh)).expect(
-                format!(
-                    "Failed to parse finsh time 1970-01-01T{}:00+02:00 as RFC3339, {raw:?}",
-                    raw.finsh
-                )
-                .as_str(),
-            ),
-            stage: raw.stage,
-            source: raw.source,
-        }
+impl RawShedding {
+    /// Parses this entry's `start`/`finsh` strings; those that carry an explicit offset keep it,
+    /// and those without one are taken as local wall-clock times in `tz`.
+    fn try_into_shedding(self, tz: Tz) -> Result<Shedding, SheddingParseError> {
+        let start = parse_datetime_field("start", &self.start, tz, false)?;
+        // `finsh` is an exclusive end bound, so a bare date rolls forward to the next midnight.
+        let finsh = parse_datetime_field("finsh", &self.finsh, tz, true)?;
+
+        Ok(Shedding {
+            start,
+            finsh,
+            stage: self.stage,
+            source: self.source,
+        })
     }
 }
 
@@ -88,7 +296,10 @@ impl From<RawShedding> for Shedding {
 /// time (but the date is always 1 Jan 1970 or 2 Jan 1970), a boolean to indicate if the start time and the end
 /// time imply the loadshedding goes over midnight (ie from 22:00 to 00:30) and the stage of the
 /// loadshedding.
-#[derive(Debug)]
+///
+/// Round-trips through serde: `start_time` and `finsh_time` serialize as RFC3339 strings,
+/// `stage` and `date_of_month` as integers, and `goes_over_midnight` as a bool.
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
 pub struct MonthlyShedding {
     /// The time when LoadShedding *should* start. The date of this member will always be 1 Jan
     /// 1970.
@@ -119,40 +330,283 @@ pub struct RawMonthlyShedding {
     date_of_month: u8,
 }
 
-impl From<RawMonthlyShedding> for MonthlyShedding {
-    fn from(raw: RawMonthlyShedding) -> Self {
-        let start = NaiveTime::parse_from_str(&raw.start_time, "%H:%M").unwrap();
-        let finsh = NaiveTime::parse_from_str(&raw.finsh_time, "%H:%M").unwrap();
+impl RawMonthlyShedding {
+    /// Parses this entry's `start_time`/`finsh_time` strings as local wall-clock times in `tz`.
+    fn try_into_monthly_shedding(self, tz: Tz) -> Result<MonthlyShedding, SheddingParseError> {
+        let start = parse_time_field("start_time", &self.start_time)?;
+        let finsh = parse_time_field("finsh_time", &self.finsh_time)?;
         let goes_over_midnight = finsh < start;
 
-        let date = if goes_over_midnight { "01" } else { "02" };
-
-        MonthlyShedding {
-            start_time: DateTime::parse_from_rfc3339(&format!(
-                "1970-01-01T{}:00+02:00",
-                raw.start_time
-            ))
-            .expect(
-                format!(
-                    "Failed to parse start time 1970-01-01T{}:00+02:00 as RFC3339, {raw:?}",
-                    raw.start_time
-                )
-                .as_str(),
-            ),
-            finsh_time: DateTime::parse_from_rfc3339(&format!(
-                "1970-01-{date}T{}:00+02:00",
-                raw.finsh_time
-            ))
-            .expect(
-                format!(
-                    "Failed to parse start time 1970-01-01T{}:00+02:00 as RFC3339, {raw:?}",
-                    raw.finsh_time
-                )
-                .as_str(),
-            ),
-            stage: raw.stage,
-            date_of_month: raw.date_of_month,
+        let finsh_date_of_month = if goes_over_midnight { 2 } else { 1 };
+
+        let start_time = resolve_local(
+            "start_time",
+            &self.start_time,
+            tz,
+            NaiveDate::from_ymd_opt(1970, 1, 1)
+                .expect("1970-01-01 is a valid date")
+                .and_time(start),
+        )?;
+        let finsh_time = resolve_local(
+            "finsh_time",
+            &self.finsh_time,
+            tz,
+            NaiveDate::from_ymd_opt(1970, 1, finsh_date_of_month)
+                .expect("1970-01-01 and 1970-01-02 are valid dates")
+                .and_time(finsh),
+        )?;
+
+        Ok(MonthlyShedding {
+            start_time,
+            finsh_time,
+            stage: self.stage,
+            date_of_month: self.date_of_month,
             goes_over_midnight,
-        }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn full_rfc3339_datetime_is_parsed() {
+        let parsed = parse_datetime_field(
+            "start",
+            "2024-06-01T18:00:00+02:00",
+            chrono_tz::Africa::Johannesburg,
+            false,
+        )
+        .expect("full RFC3339 timestamp parses");
+
+        assert_eq!(
+            parsed,
+            DateTime::parse_from_rfc3339("2024-06-01T18:00:00+02:00").unwrap()
+        );
+    }
+
+    #[test]
+    fn rfc3339_with_space_separator_is_parsed() {
+        let parsed = parse_datetime_field(
+            "start",
+            "2024-06-01 18:00:00+02:00",
+            chrono_tz::Africa::Johannesburg,
+            false,
+        )
+        .expect("RFC3339 with a space separator parses");
+
+        assert_eq!(
+            parsed,
+            DateTime::parse_from_rfc3339("2024-06-01T18:00:00+02:00").unwrap()
+        );
+    }
+
+    #[test]
+    fn bare_datetime_without_seconds_is_parsed_in_configured_timezone() {
+        let parsed = parse_datetime_field(
+            "start",
+            "2024-06-01T18:00",
+            chrono_tz::Africa::Johannesburg,
+            false,
+        )
+        .expect("bare YYYY-MM-DDTHH:MM parses");
+
+        assert_eq!(
+            parsed,
+            DateTime::parse_from_rfc3339("2024-06-01T18:00:00+02:00").unwrap()
+        );
+    }
+
+    #[test]
+    fn rfc2822_datetime_is_parsed() {
+        let parsed = parse_datetime_field(
+            "start",
+            "Sat, 1 Jun 2024 18:00:00 +0200",
+            chrono_tz::Africa::Johannesburg,
+            false,
+        )
+        .expect("RFC2822 timestamp parses");
+
+        assert_eq!(
+            parsed,
+            DateTime::parse_from_rfc3339("2024-06-01T18:00:00+02:00").unwrap()
+        );
+    }
+
+    #[test]
+    fn bare_date_is_midnight_in_configured_timezone() {
+        let parsed =
+            parse_datetime_field("start", "2024-06-01", chrono_tz::Africa::Johannesburg, false)
+                .expect("bare YYYY-MM-DD date parses");
+
+        assert_eq!(
+            parsed,
+            DateTime::parse_from_rfc3339("2024-06-01T00:00:00+02:00").unwrap()
+        );
+    }
+
+    #[test]
+    fn bare_date_rolls_to_next_midnight_for_exclusive_end_bound() {
+        let parsed =
+            parse_datetime_field("finsh", "2024-06-01", chrono_tz::Africa::Johannesburg, true)
+                .expect("bare YYYY-MM-DD date parses");
+
+        assert_eq!(
+            parsed,
+            DateTime::parse_from_rfc3339("2024-06-02T00:00:00+02:00").unwrap()
+        );
+    }
+
+    #[test]
+    fn shedding_round_trips_through_json() {
+        let raw = RawShedding {
+            start: "2024-06-01T18:00".to_string(),
+            finsh: "2024-06-01T20:30".to_string(),
+            stage: 4,
+            source: "https://example.com".to_string(),
+        };
+        let shedding = raw
+            .try_into_shedding(chrono_tz::Africa::Johannesburg)
+            .expect("valid RawShedding parses");
+
+        let json = serde_json::to_string(&shedding).expect("Shedding serializes");
+        let round_tripped: Shedding =
+            serde_json::from_str(&json).expect("serialized Shedding deserializes");
+
+        assert_eq!(shedding, round_tripped);
+    }
+
+    #[test]
+    fn monthly_shedding_round_trips_through_json() {
+        let raw = RawMonthlyShedding {
+            start_time: "22:00".to_string(),
+            finsh_time: "00:30".to_string(),
+            stage: 2,
+            date_of_month: 5,
+        };
+        let monthly = raw
+            .try_into_monthly_shedding(chrono_tz::Africa::Johannesburg)
+            .expect("valid RawMonthlyShedding parses");
+
+        let json = serde_json::to_string(&monthly).expect("MonthlyShedding serializes");
+        let round_tripped: MonthlyShedding =
+            serde_json::from_str(&json).expect("serialized MonthlyShedding deserializes");
+
+        assert_eq!(monthly, round_tripped);
+    }
+
+    #[test]
+    fn manually_input_schedule_round_trips_through_json() {
+        let raw = RawManuallyInputSchedule {
+            timezone: "Africa/Johannesburg".to_string(),
+            changes: vec![RawShedding {
+                start: "2024-06-01T18:00".to_string(),
+                finsh: "2024-06-01T20:30".to_string(),
+                stage: 4,
+                source: "https://example.com".to_string(),
+            }],
+            historical_changes: vec![],
+        };
+        let schedule: ManuallyInputSchedule = raw.try_into().expect("valid schedule parses");
+
+        let json = serde_json::to_string(&schedule).expect("ManuallyInputSchedule serializes");
+        let round_tripped: ManuallyInputSchedule =
+            serde_json::from_str(&json).expect("serialized ManuallyInputSchedule deserializes");
+
+        assert_eq!(schedule, round_tripped);
+    }
+
+    #[test]
+    fn malformed_entries_are_all_accumulated_instead_of_bailing_on_the_first() {
+        let raw = RawManuallyInputSchedule {
+            timezone: "Africa/Johannesburg".to_string(),
+            changes: vec![
+                RawShedding {
+                    start: "not a date".to_string(),
+                    finsh: "2024-06-01T20:30".to_string(),
+                    stage: 4,
+                    source: "https://example.com".to_string(),
+                },
+                RawShedding {
+                    start: "2024-06-02T18:00".to_string(),
+                    finsh: "also not a date".to_string(),
+                    stage: 4,
+                    source: "https://example.com".to_string(),
+                },
+            ],
+            historical_changes: vec![],
+        };
+
+        let errors = ManuallyInputSchedule::try_from(raw)
+            .expect_err("both malformed entries should be reported");
+
+        assert_eq!(errors.len(), 2);
+        assert!(matches!(
+            errors[0],
+            SheddingParseError::InvalidFormat { field: "start", .. }
+        ));
+        assert!(matches!(
+            errors[1],
+            SheddingParseError::InvalidFormat {
+                field: "finsh",
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn unknown_timezone_name_is_reported() {
+        let raw = RawManuallyInputSchedule {
+            timezone: "Not/AZone".to_string(),
+            changes: vec![],
+            historical_changes: vec![],
+        };
+
+        let errors = ManuallyInputSchedule::try_from(raw)
+            .expect_err("an unrecognised IANA zone name should be reported");
+
+        assert!(matches!(
+            errors.as_slice(),
+            [SheddingParseError::UnknownTimezone { value }] if value == "Not/AZone"
+        ));
+    }
+
+    #[test]
+    fn nonexistent_local_time_during_a_spring_forward_gap_is_an_error() {
+        // On 2024-03-10, America/New_York clocks jump from 02:00 to 03:00, so 02:30 never happens.
+        let err = parse_datetime_field(
+            "start",
+            "2024-03-10T02:30",
+            chrono_tz::America::New_York,
+            false,
+        )
+        .expect_err("02:30 does not exist on this date in America/New_York");
+
+        assert!(matches!(
+            err,
+            SheddingParseError::NonexistentLocalTime {
+                field: "start",
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn ambiguous_local_time_during_a_fall_back_resolves_to_the_earlier_instant() {
+        // On 2024-11-03, America/New_York clocks fall back from 02:00 to 01:00, so 01:30 happens
+        // twice: once at -04:00 (EDT) and once at -05:00 (EST). We resolve to the earlier one.
+        let parsed = parse_datetime_field(
+            "start",
+            "2024-11-03T01:30",
+            chrono_tz::America::New_York,
+            false,
+        )
+        .expect("ambiguous local times resolve rather than erroring");
+
+        assert_eq!(
+            parsed,
+            DateTime::parse_from_rfc3339("2024-11-03T01:30:00-04:00").unwrap()
+        );
     }
 }